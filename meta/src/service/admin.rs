@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+use super::cluster::ClusterHandle;
+use super::metrics::Metrics;
+use super::router::{admin_route, is_err_response, json_body};
+use super::single::SingleServer;
+use crate::store::command::*;
+use crate::store::storage::StateMachine;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTenantReq {
+    pub name: String,
+    #[serde(default)]
+    pub options: Option<models::schema::TenantOptions>,
+}
+
+fn admin_reply(encoded: String, ok_status: StatusCode, err_status: StatusCode) -> impl Reply {
+    let status = if is_err_response(&encoded) {
+        err_status
+    } else {
+        ok_status
+    };
+    warp::reply::with_status(
+        warp::reply::with_header(encoded, "content-type", "application/json"),
+        status,
+    )
+}
+
+impl SingleServer {
+    /// Resource-oriented `/admin/...` routes, parallel to the raw `/read` and
+    /// `/write` endpoints: each one maps to a `ReadCommand`/`WriteCommand` the same
+    /// way `read()`/`write()` do, but returns typed JSON with proper 200/201/404/409
+    /// status codes instead of an opaque 200-always blob.
+    pub(super) fn admin(
+        &self,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        self.admin_list_tenants()
+            .or(self.admin_create_tenant())
+            .or(self.admin_list_databases())
+            .or(self.admin_list_users())
+            .or(self.admin_delete_user())
+    }
+
+    fn admin_list_tenants(
+        &self,
+    ) -> warp::filters::BoxedFilter<(impl Reply,)> {
+        admin_route!(
+            get,
+            ("admin" / "tenants"),
+            self.with_storage().and(self.with_metrics()),
+            |storage: Arc<RwLock<StateMachine>>, metrics: Arc<Metrics>| async move {
+                let rsp = storage
+                    .read()
+                    .await
+                    .process_read_command(&ReadCommand::Tenants);
+                metrics.inc_read();
+                let res: Result<_, Rejection> =
+                    Ok(admin_reply(rsp, StatusCode::OK, StatusCode::NOT_FOUND));
+                res
+            }
+        )
+    }
+
+    fn admin_create_tenant(
+        &self,
+    ) -> warp::filters::BoxedFilter<(impl Reply,)> {
+        admin_route!(
+            post,
+            ("admin" / "tenants"),
+            self.with_storage()
+                .and(json_body::<CreateTenantReq>())
+                .and(self.with_metrics())
+                .and(self.with_cluster()),
+            |storage: Arc<RwLock<StateMachine>>,
+             req: CreateTenantReq,
+             metrics: Arc<Metrics>,
+             cluster: Option<Arc<ClusterHandle>>| async move {
+                let cmd = WriteCommand::CreateTenant(req.name, req.options.unwrap_or_default());
+                let rsp = SingleServer::process_write(cmd, storage, cluster)
+                    .await
+                    .map_err(warp::reject::custom)?;
+                metrics.inc_write();
+                let res: Result<_, Rejection> =
+                    Ok(admin_reply(rsp, StatusCode::CREATED, StatusCode::CONFLICT));
+                res
+            }
+        )
+    }
+
+    fn admin_list_databases(
+        &self,
+    ) -> warp::filters::BoxedFilter<(impl Reply,)> {
+        admin_route!(
+            get,
+            ("admin" / "tenants" / String / "databases"),
+            self.with_storage().and(self.with_metrics()),
+            |tenant: String, storage: Arc<RwLock<StateMachine>>, metrics: Arc<Metrics>| async move {
+                let rsp = storage
+                    .read()
+                    .await
+                    .process_read_command(&ReadCommand::DatabaseNames(tenant));
+                metrics.inc_read();
+                let res: Result<_, Rejection> =
+                    Ok(admin_reply(rsp, StatusCode::OK, StatusCode::NOT_FOUND));
+                res
+            }
+        )
+    }
+
+    fn admin_list_users(
+        &self,
+    ) -> warp::filters::BoxedFilter<(impl Reply,)> {
+        admin_route!(
+            get,
+            ("admin" / "users"),
+            self.with_storage().and(self.with_metrics()),
+            |storage: Arc<RwLock<StateMachine>>, metrics: Arc<Metrics>| async move {
+                let rsp = storage
+                    .read()
+                    .await
+                    .process_read_command(&ReadCommand::Users);
+                metrics.inc_read();
+                let res: Result<_, Rejection> =
+                    Ok(admin_reply(rsp, StatusCode::OK, StatusCode::NOT_FOUND));
+                res
+            }
+        )
+    }
+
+    fn admin_delete_user(
+        &self,
+    ) -> warp::filters::BoxedFilter<(impl Reply,)> {
+        admin_route!(
+            delete,
+            ("admin" / "users" / String),
+            self.with_storage()
+                .and(self.with_metrics())
+                .and(self.with_cluster()),
+            |name: String,
+             storage: Arc<RwLock<StateMachine>>,
+             metrics: Arc<Metrics>,
+             cluster: Option<Arc<ClusterHandle>>| async move {
+                let rsp = SingleServer::process_write(WriteCommand::DropUser(name), storage, cluster)
+                    .await
+                    .map_err(warp::reject::custom)?;
+                metrics.inc_write();
+                let res: Result<_, Rejection> =
+                    Ok(admin_reply(rsp, StatusCode::OK, StatusCode::NOT_FOUND));
+                res
+            }
+        )
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+struct PeerState {
+    addr: String,
+    alive: bool,
+    last_seen: Instant,
+}
+
+/// Tracks peer addresses and simple up/down liveness for a meta cluster. Liveness is
+/// driven by the periodic heartbeat in `ClusterHandle::run_heartbeat`, plus a peer's
+/// `Join` (which registers it as alive via `add_peer`) -- this type just holds the
+/// last known state for whoever needs it (leader election, the
+/// `/admin` surface, ...).
+pub struct Membership {
+    self_id: u64,
+    self_addr: String,
+    peers: RwLock<HashMap<u64, PeerState>>,
+    /// Addresses configured at startup that haven't reported a real node id yet (no
+    /// successful heartbeat, ping, or join). Kept separate from `peers` rather than
+    /// seeded under a synthesized id: a placeholder id can collide with a real one
+    /// (`self_id`, or another peer's) before that peer's first heartbeat reconciles
+    /// it, which would let `lowest_alive_id`/`addr_of` pick or report the wrong node
+    /// during that window. An address here is simply not a leader-election candidate
+    /// yet.
+    pending: RwLock<Vec<String>>,
+}
+
+impl Membership {
+    pub fn new(self_id: u64, self_addr: String, peer_addrs: Vec<String>) -> Self {
+        Self {
+            self_id,
+            self_addr,
+            peers: RwLock::new(HashMap::new()),
+            pending: RwLock::new(peer_addrs),
+        }
+    }
+
+    pub fn self_id(&self) -> u64 {
+        self.self_id
+    }
+
+    /// Every address we know about, reconciled or not -- used to drive heartbeats and
+    /// replication, which need to reach a peer regardless of whether we've learned its
+    /// node id yet.
+    pub fn peer_addrs(&self) -> Vec<String> {
+        let mut addrs: Vec<String> = self
+            .peers
+            .read()
+            .unwrap()
+            .values()
+            .map(|p| p.addr.clone())
+            .collect();
+        addrs.extend(self.pending.read().unwrap().iter().cloned());
+        addrs
+    }
+
+    pub fn add_peer(&self, id: u64, addr: String) {
+        self.pending.write().unwrap().retain(|a| a != &addr);
+        let mut peers = self.peers.write().unwrap();
+        peers.retain(|_, peer| peer.addr != addr);
+        peers.insert(
+            id,
+            PeerState {
+                addr,
+                alive: true,
+                last_seen: Instant::now(),
+            },
+        );
+    }
+
+    /// Marks the peer we just heard back from on `addr` alive under its real
+    /// `node_id`, moving it out of `pending` (or replacing an earlier, possibly
+    /// different, id it reported before).
+    pub fn mark_alive(&self, addr: &str, node_id: u64) {
+        self.pending.write().unwrap().retain(|a| a != addr);
+        let mut peers = self.peers.write().unwrap();
+        peers.retain(|id, peer| *id == node_id || peer.addr != addr);
+        peers.insert(
+            node_id,
+            PeerState {
+                addr: addr.to_string(),
+                alive: true,
+                last_seen: Instant::now(),
+            },
+        );
+    }
+
+    pub fn mark_unreachable(&self, addr: &str) {
+        if let Some(peer) = self
+            .peers
+            .write()
+            .unwrap()
+            .values_mut()
+            .find(|p| p.addr == addr)
+        {
+            peer.alive = false;
+        }
+    }
+
+    pub fn addr_of(&self, id: u64) -> Option<String> {
+        if id == self.self_id {
+            return Some(self.self_addr.clone());
+        }
+        self.peers.read().unwrap().get(&id).map(|p| p.addr.clone())
+    }
+
+    /// Lowest node id currently believed alive, including self -- a stand-in for real
+    /// leader election. Good enough to pick a single, deterministic leader as long as
+    /// heartbeats are flowing; a production cluster would want Raft (or similar)
+    /// driving this instead. Peers still in `pending` (no reconciled id) are never
+    /// candidates.
+    pub fn lowest_alive_id(&self) -> Option<u64> {
+        let mut candidates: Vec<u64> = self
+            .peers
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, peer)| peer.alive)
+            .map(|(id, _)| *id)
+            .collect();
+        candidates.push(self.self_id);
+        candidates.into_iter().min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_peers_are_pending_not_synthesized_ids() {
+        let membership = Membership::new(1, "self:1".to_string(), vec!["peer:1".to_string()]);
+        // No reconciled peer yet, so the only leader candidate is self.
+        assert_eq!(membership.lowest_alive_id(), Some(1));
+        assert_eq!(membership.peer_addrs(), vec!["peer:1".to_string()]);
+    }
+
+    #[test]
+    fn mark_alive_reconciles_a_pending_peer_by_address() {
+        let membership = Membership::new(1, "self:1".to_string(), vec!["peer:1".to_string()]);
+        membership.mark_alive("peer:1", 2);
+        assert_eq!(membership.addr_of(2), Some("peer:1".to_string()));
+        assert_eq!(membership.lowest_alive_id(), Some(1));
+        // Still reachable exactly once, not duplicated between pending and peers.
+        assert_eq!(membership.peer_addrs(), vec!["peer:1".to_string()]);
+    }
+
+    #[test]
+    fn unreconciled_peer_cannot_collide_with_self_id() {
+        // A peer configured by address only, before its first heartbeat, must never
+        // be picked as leader just because it happened to share self's id.
+        let membership = Membership::new(1, "self:1".to_string(), vec!["peer:1".to_string()]);
+        assert_eq!(membership.lowest_alive_id(), Some(1));
+        membership.mark_alive("peer:1", 1);
+        // Once reconciled under the same id as self (a misconfiguration), it's still
+        // just one candidate, not two.
+        assert_eq!(membership.lowest_alive_id(), Some(1));
+    }
+
+    #[test]
+    fn mark_unreachable_only_affects_reconciled_peers() {
+        let membership = Membership::new(1, "self:1".to_string(), vec!["peer:1".to_string()]);
+        // A pending peer has no liveness state to clear yet; this must not panic.
+        membership.mark_unreachable("peer:1");
+        membership.mark_alive("peer:1", 2);
+        membership.mark_unreachable("peer:1");
+        assert_eq!(membership.lowest_alive_id(), Some(1));
+    }
+}
@@ -0,0 +1,235 @@
+pub mod membership;
+pub mod rpc;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use self::membership::Membership;
+use self::rpc::{RpcClient, RpcRequest, RpcResponse, RpcServer};
+use super::metrics::Metrics;
+use super::single::BatchRequest;
+use crate::error::{MetaError, MetaResult};
+use crate::store::command::WriteCommand;
+use crate::store::storage::StateMachine;
+
+const NO_LEADER: u64 = u64::MAX;
+
+/// Static description of a meta cluster: this node's id/address and the addresses of
+/// its peers. Peers are resolved once at startup and then tracked by `Membership`;
+/// there is no separate service-discovery integration.
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    pub node_id: u64,
+    pub addr: String,
+    pub peers: Vec<String>,
+    pub heartbeat_interval: Duration,
+}
+
+/// The path from a single embedded `StateMachine` to a fault-tolerant meta cluster:
+/// ties peer liveness tracking (`Membership`) and the RPC transport together, and
+/// exposes the two things the HTTP layer needs -- who the leader currently is, and
+/// how to get a write to the rest of the group before acking it.
+pub struct ClusterHandle {
+    config: ClusterConfig,
+    membership: Arc<Membership>,
+    rpc: RpcClient,
+    leader_id: AtomicU64,
+}
+
+impl ClusterHandle {
+    /// Starts the RPC server, begins heartbeating peers, and -- if this node is
+    /// joining an already-running cluster -- pulls a full snapshot from an existing
+    /// peer into `storage` so it starts caught up before entering the replication
+    /// stream.
+    pub async fn start(
+        config: ClusterConfig,
+        storage: Arc<RwLock<StateMachine>>,
+        metrics: Arc<Metrics>,
+    ) -> MetaResult<Arc<Self>> {
+        let membership = Arc::new(Membership::new(
+            config.node_id,
+            config.addr.clone(),
+            config.peers.clone(),
+        ));
+        let rpc = RpcClient::new();
+
+        let handle = Arc::new(ClusterHandle {
+            config: config.clone(),
+            membership,
+            rpc,
+            leader_id: AtomicU64::new(NO_LEADER),
+        });
+
+        if let Some(snapshot) = handle.bootstrap_join().await? {
+            storage.write().await.restore(&snapshot).await?;
+        }
+
+        RpcServer::spawn(config.addr.clone(), storage, metrics, handle.clone());
+
+        let heartbeat = handle.clone();
+        tokio::spawn(async move { heartbeat.run_heartbeat().await });
+
+        Ok(handle)
+    }
+
+    /// Pulls a `dump()` snapshot from the first reachable configured peer, for a node
+    /// joining an existing cluster. Returns `None` when there are no peers to join
+    /// (this node is bootstrapping a brand-new cluster).
+    async fn bootstrap_join(&self) -> MetaResult<Option<String>> {
+        for peer in &self.config.peers {
+            let req = RpcRequest::Join {
+                node_id: self.config.node_id,
+                addr: self.config.addr.clone(),
+            };
+            if let Ok(RpcResponse::Snapshot(snapshot)) = self.rpc.call(peer, req).await {
+                return Ok(Some(snapshot));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn run_heartbeat(&self) {
+        let mut tick = tokio::time::interval(self.config.heartbeat_interval);
+        loop {
+            tick.tick().await;
+            for peer in self.membership.peer_addrs() {
+                let req = RpcRequest::Ping {
+                    node_id: self.config.node_id,
+                };
+                match self.rpc.call(&peer, req).await {
+                    Ok(RpcResponse::Pong { node_id }) => {
+                        self.membership.mark_alive(&peer, node_id)
+                    }
+                    _ => self.membership.mark_unreachable(&peer),
+                }
+            }
+            self.elect_leader();
+        }
+    }
+
+    fn elect_leader(&self) {
+        if let Some(leader) = self.membership.lowest_alive_id() {
+            self.leader_id.store(leader, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader_id.load(Ordering::SeqCst) == self.config.node_id
+    }
+
+    pub fn leader_addr(&self) -> Option<String> {
+        match self.leader_id.load(Ordering::SeqCst) {
+            NO_LEADER => None,
+            id => self.membership.addr_of(id),
+        }
+    }
+
+    pub(crate) fn membership(&self) -> &Membership {
+        &self.membership
+    }
+
+    /// Replicates a write to every known follower and waits for each to ack before
+    /// returning, so the write is only considered committed once the whole group has
+    /// it. Only meaningful when called on the leader, which applies the write itself
+    /// only after this returns (see `replicate_and_apply`).
+    ///
+    /// Used both for the leader's own direct writes and, via `replicate_and_apply`, for
+    /// a write a follower forwarded to us -- in the latter case this also reaches the
+    /// forwarding follower itself, which is exactly what gives it a copy of a command
+    /// it never applied locally.
+    pub async fn replicate(&self, command: &WriteCommand) -> MetaResult<()> {
+        let calls = self.membership.peer_addrs().into_iter().map(|peer| {
+            let req = RpcRequest::Replicate {
+                command: command.clone(),
+            };
+            let rpc = self.rpc.clone();
+            async move { rpc.call(&peer, req).await }
+        });
+        // Fan out to every follower concurrently -- a single slow or unreachable peer
+        // should add its own latency to the write, not every other peer's latency on
+        // top of it.
+        futures::future::try_join_all(calls).await?;
+        Ok(())
+    }
+
+    /// Replicates `command` to every follower and then applies it to `storage`,
+    /// returning the encoded response -- the leader-side half of every write,
+    /// regardless of whether it originated on this node or was forwarded here by a
+    /// follower. Replicating before applying means a replication failure leaves the
+    /// leader's own state untouched too, so the caller's error and the leader's state
+    /// agree (a safe command to retry, not one already applied once and reported as
+    /// failed).
+    pub async fn replicate_and_apply(
+        &self,
+        command: &WriteCommand,
+        storage: &RwLock<StateMachine>,
+    ) -> MetaResult<String> {
+        self.replicate(command).await?;
+        Ok(storage.write().await.process_write_command(command))
+    }
+
+    /// Forwards a write a follower received to the current leader, which replicates
+    /// and applies it there (see `replicate_and_apply`) before acking back -- unlike
+    /// `replicate`'s passive `Replicate` request, `Forward` makes the leader fan the
+    /// write back out to the rest of the group instead of only ever applying it
+    /// locally.
+    pub async fn forward_to_leader(&self, command: &WriteCommand) -> MetaResult<String> {
+        let leader = self.leader_addr().ok_or_else(|| {
+            MetaError::from(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "no leader elected",
+            ))
+        })?;
+
+        let req = RpcRequest::Forward {
+            command: command.clone(),
+        };
+        match self.rpc.call(&leader, req).await? {
+            RpcResponse::Ack(rsp) => Ok(rsp),
+            other => Err(MetaError::from(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("unexpected leader response: {:?}", other),
+            ))),
+        }
+    }
+
+    /// Replicates an already-committed `/batch` request's write commands to every
+    /// follower, in order, under one `storage.write()` lock on each -- the batch
+    /// counterpart of `replicate`. Only meaningful when called on the leader, after it
+    /// has already applied the batch locally.
+    pub async fn replicate_batch(&self, commands: &[WriteCommand]) -> MetaResult<()> {
+        let calls = self.membership.peer_addrs().into_iter().map(|peer| {
+            let req = RpcRequest::ReplicateBatch {
+                commands: commands.to_vec(),
+            };
+            let rpc = self.rpc.clone();
+            async move { rpc.call(&peer, req).await }
+        });
+        futures::future::try_join_all(calls).await?;
+        Ok(())
+    }
+
+    /// Forwards a `/batch` request a follower received to the current leader, which
+    /// runs it there -- replicating its write commands to the rest of the group once
+    /// committed -- before acking back. The batch counterpart of `forward_to_leader`.
+    pub async fn forward_batch_to_leader(&self, req: &BatchRequest) -> MetaResult<String> {
+        let leader = self.leader_addr().ok_or_else(|| {
+            MetaError::from(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "no leader elected",
+            ))
+        })?;
+
+        let req = RpcRequest::ForwardBatch(req.clone());
+        match self.rpc.call(&leader, req).await? {
+            RpcResponse::Ack(rsp) => Ok(rsp),
+            other => Err(MetaError::from(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("unexpected leader response: {:?}", other),
+            ))),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, RwLock};
+
+use super::ClusterHandle;
+use crate::error::{MetaError, MetaResult};
+use crate::service::metrics::Metrics;
+use crate::service::single::{BatchRequest, SingleServer};
+use crate::store::command::WriteCommand;
+use crate::store::storage::StateMachine;
+
+/// Messages exchanged between meta nodes. Wire format is a 4-byte big-endian length
+/// prefix followed by a MessagePack-encoded frame, sent over a TCP connection kept
+/// open and reused across calls (in the style of a netapp-like transport) rather than
+/// reconnecting per request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RpcRequest {
+    Ping { node_id: u64 },
+    Join { node_id: u64, addr: String },
+    /// Leader -> follower: apply `command` locally. Passive -- the receiver never
+    /// re-replicates it any further.
+    Replicate { command: WriteCommand },
+    /// Follower -> leader: a write the follower received on its own `/write` that it
+    /// can't apply itself, since only the leader is allowed to. The leader replicates
+    /// it to the rest of the group (via `Replicate`, which reaches the forwarding
+    /// follower too) and applies it locally before acking.
+    Forward { command: WriteCommand },
+    /// Leader -> follower: apply an already-committed `/batch` request's write
+    /// commands, in order, under one lock. The batch counterpart of `Replicate`.
+    ReplicateBatch { commands: Vec<WriteCommand> },
+    /// Follower -> leader: a `/batch` request the follower received that it can't run
+    /// itself. The batch counterpart of `Forward`.
+    ForwardBatch(BatchRequest),
+    Snapshot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RpcResponse {
+    Pong { node_id: u64 },
+    Snapshot(String),
+    Ack(String),
+    Error(String),
+}
+
+async fn write_frame<T: Serialize>(stream: &mut TcpStream, value: &T) -> MetaResult<()> {
+    let payload = rmp_serde::to_vec(value).map_err(|e| {
+        MetaError::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    })?;
+    stream
+        .write_u32(payload.len() as u32)
+        .await
+        .map_err(MetaError::from)?;
+    stream.write_all(&payload).await.map_err(MetaError::from)?;
+    Ok(())
+}
+
+async fn read_frame<T: DeserializeOwned>(stream: &mut TcpStream) -> MetaResult<T> {
+    let len = stream.read_u32().await.map_err(MetaError::from)?;
+    let mut buf = vec![0u8; len as usize];
+    stream.read_exact(&mut buf).await.map_err(MetaError::from)?;
+    rmp_serde::from_slice(&buf)
+        .map_err(|e| MetaError::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+}
+
+/// Client side of the inter-node RPC transport: one persistent TCP connection per
+/// peer address, reconnected lazily the next time that peer is called if the
+/// connection was dropped. Each peer gets its own connection lock, so a slow or
+/// unreachable peer only serializes calls to *that* peer, not to every other peer
+/// sharing this client.
+#[derive(Clone)]
+pub struct RpcClient {
+    conns: Arc<Mutex<HashMap<String, Arc<Mutex<Option<TcpStream>>>>>>,
+}
+
+impl RpcClient {
+    pub fn new() -> Self {
+        Self {
+            conns: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn conn_for(&self, addr: &str) -> Arc<Mutex<Option<TcpStream>>> {
+        let mut conns = self.conns.lock().await;
+        conns
+            .entry(addr.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(None)))
+            .clone()
+    }
+
+    pub async fn call(&self, addr: &str, req: RpcRequest) -> MetaResult<RpcResponse> {
+        let slot = self.conn_for(addr).await;
+        let mut slot = slot.lock().await;
+
+        let mut stream = match slot.take() {
+            Some(stream) => stream,
+            None => TcpStream::connect(addr).await.map_err(MetaError::from)?,
+        };
+
+        let result: MetaResult<RpcResponse> = async {
+            write_frame(&mut stream, &req).await?;
+            read_frame::<RpcResponse>(&mut stream).await
+        }
+        .await;
+
+        // Only keep the connection around if it's still in a usable state; a failed
+        // call most likely means the peer closed it.
+        if result.is_ok() {
+            *slot = Some(stream);
+        }
+        result
+    }
+}
+
+impl Default for RpcClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Server side of the inter-node RPC transport: accepts peer connections and keeps
+/// each one open, serving `Ping`/`Join`/`Replicate`/`Forward`/`ReplicateBatch`/
+/// `ForwardBatch`/`Snapshot` requests off it until the peer disconnects.
+pub struct RpcServer;
+
+impl RpcServer {
+    pub fn spawn(
+        addr: String,
+        storage: Arc<RwLock<StateMachine>>,
+        metrics: Arc<Metrics>,
+        cluster: Arc<ClusterHandle>,
+    ) {
+        tokio::spawn(async move {
+            let listener = match TcpListener::bind(&addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::error!("cluster rpc server failed to bind {}: {}", addr, e);
+                    return;
+                }
+            };
+            tracing::info!("cluster rpc server listening on {}", addr);
+
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        tracing::warn!("cluster rpc accept error: {}", e);
+                        continue;
+                    }
+                };
+                let storage = storage.clone();
+                let metrics = metrics.clone();
+                let cluster = cluster.clone();
+                tokio::spawn(
+                    async move { Self::serve_conn(stream, storage, metrics, cluster).await },
+                );
+            }
+        });
+    }
+
+    async fn serve_conn(
+        mut stream: TcpStream,
+        storage: Arc<RwLock<StateMachine>>,
+        metrics: Arc<Metrics>,
+        cluster: Arc<ClusterHandle>,
+    ) {
+        loop {
+            let req: RpcRequest = match read_frame(&mut stream).await {
+                Ok(req) => req,
+                Err(_) => return,
+            };
+
+            let rsp = match req {
+                RpcRequest::Ping { node_id: _ } => {
+                    // Liveness is tracked unidirectionally, from whichever side
+                    // dialed out (see `ClusterHandle::run_heartbeat`): the incoming
+                    // TCP source address here is an ephemeral port, not the peer's
+                    // listening address, so it can't be reconciled against
+                    // `Membership`'s peer list. Just answer the ping.
+                    RpcResponse::Pong {
+                        node_id: cluster.membership().self_id(),
+                    }
+                }
+                RpcRequest::Join { node_id, addr } => {
+                    cluster.membership().add_peer(node_id, addr);
+                    match storage.read().await.dump().await {
+                        Ok(snapshot) => RpcResponse::Snapshot(snapshot),
+                        Err(e) => RpcResponse::Error(e.to_string()),
+                    }
+                }
+                RpcRequest::Replicate { command } => {
+                    let rsp = storage.write().await.process_write_command(&command);
+                    RpcResponse::Ack(rsp)
+                }
+                RpcRequest::Forward { command } => {
+                    if !cluster.is_leader() {
+                        RpcResponse::Error("not the leader".to_string())
+                    } else {
+                        match cluster.replicate_and_apply(&command, &storage).await {
+                            Ok(rsp) => RpcResponse::Ack(rsp),
+                            Err(e) => RpcResponse::Error(e.to_string()),
+                        }
+                    }
+                }
+                RpcRequest::ReplicateBatch { commands } => {
+                    let mut guard = storage.write().await;
+                    let mut rsp = String::new();
+                    for command in &commands {
+                        rsp = guard.process_write_command(command);
+                        metrics.inc_write();
+                    }
+                    RpcResponse::Ack(rsp)
+                }
+                RpcRequest::ForwardBatch(req) => {
+                    if !cluster.is_leader() {
+                        RpcResponse::Error("not the leader".to_string())
+                    } else {
+                        let result = SingleServer::process_batch(
+                            req,
+                            storage.clone(),
+                            metrics.clone(),
+                            Some(cluster.clone()),
+                        )
+                        .await;
+                        match result {
+                            Ok(rsp) => RpcResponse::Ack(rsp),
+                            Err(e) => RpcResponse::Error(e.to_string()),
+                        }
+                    }
+                }
+                RpcRequest::Snapshot => match storage.read().await.dump().await {
+                    Ok(snapshot) => RpcResponse::Snapshot(snapshot),
+                    Err(e) => RpcResponse::Error(e.to_string()),
+                },
+            };
+
+            if write_frame(&mut stream, &rsp).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(req: &RpcRequest) -> RpcRequest {
+        rmp_serde::from_slice(&rmp_serde::to_vec(req).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn ping_roundtrips_over_messagepack() {
+        let req = RpcRequest::Ping { node_id: 7 };
+        assert!(matches!(roundtrip(&req), RpcRequest::Ping { node_id: 7 }));
+    }
+
+    #[test]
+    fn forward_roundtrips_the_wrapped_command() {
+        let req = RpcRequest::Forward {
+            command: WriteCommand::DropUser("alice".to_string()),
+        };
+        match roundtrip(&req) {
+            RpcRequest::Forward {
+                command: WriteCommand::DropUser(name),
+            } => assert_eq!(name, "alice"),
+            other => panic!("unexpected roundtrip result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn forward_and_replicate_are_distinct_on_the_wire() {
+        let command = WriteCommand::DropUser("alice".to_string());
+        let forward = rmp_serde::to_vec(&RpcRequest::Forward {
+            command: command.clone(),
+        })
+        .unwrap();
+        let replicate = rmp_serde::to_vec(&RpcRequest::Replicate { command }).unwrap();
+        // Same payload, different request type -- the leader must be able to tell a
+        // follower's forwarded write apart from a passive replication push.
+        assert_ne!(forward, replicate);
+    }
+}
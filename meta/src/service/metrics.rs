@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder};
+
+use crate::store::storage::StateMachine;
+
+/// Operational counters for a `SingleServer`, exported via `/metrics` in the
+/// Prometheus text exposition format so meta nodes can be scraped the same way the
+/// rest of the stack is.
+pub struct Metrics {
+    registry: Registry,
+    read_total: IntCounter,
+    write_total: IntCounter,
+    watch_total: IntCounter,
+    watch_latency: Histogram,
+    watch_active: IntGauge,
+    state_machine_size: IntGauge,
+    max_change_log_version: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let read_total =
+            IntCounter::new("meta_read_total", "number of read commands processed").unwrap();
+        let write_total =
+            IntCounter::new("meta_write_total", "number of write commands processed").unwrap();
+        let watch_total =
+            IntCounter::new("meta_watch_total", "number of watch commands processed").unwrap();
+        let watch_latency = Histogram::with_opts(HistogramOpts::new(
+            "meta_watch_latency_seconds",
+            "latency of watch long-polls, in seconds",
+        ))
+        .unwrap();
+        let watch_active = IntGauge::new(
+            "meta_watch_active",
+            "number of watch long-polls currently open",
+        )
+        .unwrap();
+        let state_machine_size = IntGauge::new(
+            "meta_state_machine_size_bytes",
+            "approximate size of the state machine, in bytes",
+        )
+        .unwrap();
+        let max_change_log_version = IntGauge::new(
+            "meta_max_change_log_version",
+            "current max change-log version",
+        )
+        .unwrap();
+
+        registry.register(Box::new(read_total.clone())).unwrap();
+        registry.register(Box::new(write_total.clone())).unwrap();
+        registry.register(Box::new(watch_total.clone())).unwrap();
+        registry
+            .register(Box::new(watch_latency.clone()))
+            .unwrap();
+        registry.register(Box::new(watch_active.clone())).unwrap();
+        registry
+            .register(Box::new(state_machine_size.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(max_change_log_version.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            read_total,
+            write_total,
+            watch_total,
+            watch_latency,
+            watch_active,
+            state_machine_size,
+            max_change_log_version,
+        }
+    }
+
+    pub fn inc_read(&self) {
+        self.read_total.inc();
+    }
+
+    pub fn inc_write(&self) {
+        self.write_total.inc();
+    }
+
+    pub fn inc_watch(&self) {
+        self.watch_total.inc();
+    }
+
+    pub fn observe_watch_latency(&self, secs: f64) {
+        self.watch_latency.observe(secs);
+    }
+
+    /// Returns a guard that increments the active-watch gauge and decrements it again
+    /// when dropped, so it stays accurate across early returns and cancellations.
+    pub fn track_watch(&self) -> WatchGuard<'_> {
+        self.watch_active.inc();
+        WatchGuard { metrics: self }
+    }
+
+    /// Like `track_watch`, but holds an owned `Arc<Metrics>` rather than borrowing one,
+    /// for `/watch_stream`: the active period there spans the whole streamed response
+    /// -- held inside a long-lived `WatchLogStream` -- not just one filter handler.
+    pub fn track_watch_owned(metrics: Arc<Metrics>) -> OwnedWatchGuard {
+        metrics.watch_active.inc();
+        OwnedWatchGuard { metrics }
+    }
+
+    pub fn refresh_state_machine_gauges(&self, storage: &StateMachine) {
+        self.state_machine_size.set(storage.data_size() as i64);
+        self.max_change_log_version
+            .set(storage.version() as i64);
+    }
+
+    /// Renders all registered metrics in the Prometheus text exposition format.
+    pub fn gather(&self) -> String {
+        let metric_families = self.registry.gather();
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&metric_families, &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct WatchGuard<'a> {
+    metrics: &'a Metrics,
+}
+
+impl Drop for WatchGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.watch_active.dec();
+    }
+}
+
+pub struct OwnedWatchGuard {
+    metrics: Arc<Metrics>,
+}
+
+impl Drop for OwnedWatchGuard {
+    fn drop(&mut self) {
+        self.metrics.watch_active.dec();
+    }
+}
@@ -0,0 +1,25 @@
+//! HTTP-facing meta server: request routing (`single`, `admin`, `router`), a small
+//! clustering subsystem (`cluster`), metrics (`metrics`), and bootstrap (`init`).
+//!
+//! This snapshot doesn't carry the crate's `Cargo.toml`, so the external dependencies
+//! this module surface has picked up along the way are noted here instead of in a
+//! diff to a manifest that isn't part of the tree:
+//! - `futures` (stream combinators in `single`, `try_join_all` in `cluster`)
+//! - `tokio-stream`, for `wrappers::BroadcastStream` in `single`
+//! - `prometheus`, for the counters/gauges/histograms in `metrics`
+//! - `rmp_serde`, for the MessagePack wire format in `cluster::rpc`
+//! - `rustls-native-certs` and `pem`, for the optional system-root trust in `single`'s
+//!   TLS config
+//! All of the above need an entry in the real `Cargo.toml` for this crate to build;
+//! adding one here would mean inventing a manifest for a tree that doesn't have one.
+//!
+//! Relatedly, `single::SingleServer::process_batch` calls a
+//! `StateMachine::process_write_command_quiet` that would need adding alongside
+//! `process_write_command` in `store/storage.rs` -- also not part of this snapshot.
+
+pub mod admin;
+pub mod cluster;
+pub mod init;
+pub mod metrics;
+mod router;
+pub mod single;
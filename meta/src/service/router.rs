@@ -0,0 +1,54 @@
+use serde::de::DeserializeOwned;
+use warp::hyper::body::Bytes;
+use warp::{Filter, Rejection};
+
+use crate::error::MetaError;
+
+/// Parses the request body as JSON, converting decode failures into the same
+/// `MetaError` rejection every other endpoint already uses. Replaces the
+/// `warp::body::bytes().and_then(|b| serde_json::from_slice(&b)...)` boilerplate that
+/// used to be duplicated in `read()`/`write()`/`watch()`.
+pub fn json_body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send,
+{
+    warp::body::bytes().and_then(|body: Bytes| async move {
+        serde_json::from_slice::<T>(&body)
+            .map_err(MetaError::from)
+            .map_err(warp::reject::custom)
+    })
+}
+
+/// Declares one `admin` endpoint: HTTP method, `warp::path!` segments, the filter
+/// supplying its dependencies (storage, an optional JSON body, path params, ...), and
+/// the handler, e.g.:
+///
+/// ```ignore
+/// admin_route!(get, ("admin" / "tenants"), self.with_storage(), Self::list_tenants)
+/// ```
+///
+/// expands to the `warp::path!(...).and(warp::get()).and(...).and_then(...)` filter
+/// every route would otherwise hand-assemble, boxed so heterogeneous routes can be
+/// combined with a plain chain of `.or(...)`.
+macro_rules! admin_route {
+    ($method:ident, ($($seg:tt)/+), $with:expr, $handler:expr) => {
+        warp::path!($($seg)/+)
+            .and(warp::$method())
+            .and($with)
+            .and_then($handler)
+            .boxed()
+    };
+}
+
+pub(super) use admin_route;
+
+/// Whether the JSON encoded by `response_encode` carries an `Err` variant, so a
+/// handler can translate it into the matching HTTP status (or batch failure index)
+/// instead of always answering 200 the way the raw `read`/`write`/`watch` endpoints
+/// do.
+pub fn is_err_response(encoded: &str) -> bool {
+    matches!(
+        serde_json::from_str::<serde_json::Value>(encoded),
+        Ok(serde_json::Value::Object(map)) if map.contains_key("Err")
+    )
+}